@@ -1,16 +1,26 @@
 use nats;
-use std::{io, num::NonZeroUsize, thread, time::Duration, time::Instant};
+use std::{
+    io,
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+    time::Instant,
+};
 
 use historian::Histo;
 use rand::prelude::*;
-use rand_distr::WeightedAliasIndex;
+use rand_distr::{Exp, LogNormal, WeightedAliasIndex};
 use spinners::{Spinner, Spinners};
 use structopt::StructOpt;
 
 use crossterm::style::Styler;
 
 use tui::{
-    backend::CrosstermBackend,
+    backend::{Backend, CrosstermBackend},
     layout::Rect,
     style::{Color, Modifier, Style},
     widgets::{BarChart, Block, Borders},
@@ -23,6 +33,26 @@ struct Args {
     #[structopt(long, short, default_value = "127.0.0.1")]
     server: String,
 
+    /// Username for user/password authentication.
+    #[structopt(long, requires = "pass")]
+    user: Option<String>,
+
+    /// Password for user/password authentication.
+    #[structopt(long, requires = "user")]
+    pass: Option<String>,
+
+    /// Authentication token.
+    #[structopt(long)]
+    token: Option<String>,
+
+    /// Path to a NATS credentials (.creds) file.
+    #[structopt(long, parse(from_os_str))]
+    creds: Option<std::path::PathBuf>,
+
+    /// Require a TLS connection to the server.
+    #[structopt(long)]
+    tls: bool,
+
     /// Number of service responders.
     #[structopt(long, short = "w", default_value = "1")]
     num_responders: NonZeroUsize,
@@ -34,34 +64,386 @@ struct Args {
     /// Number of service requests.
     #[structopt(long, short = "n", default_value = "100")]
     num_requests: NonZeroUsize,
+
+    /// Number of requests kept in-flight at once. In the default closed-loop
+    /// mode 1 keeps the original serial behavior and higher values issue
+    /// requests concurrently. In open-loop (--rate) mode the default of 1 does
+    /// NOT mean one in-flight: dispatch runs through a large pool so the
+    /// schedule is honored regardless of local core count; pass -c N with
+    /// N > 1 there to cap the concurrent workers at N (an explicit -c 1 is
+    /// treated the same as the default).
+    #[structopt(long, short = "c", default_value = "1")]
+    concurrency: NonZeroUsize,
+
+    /// Redraw the percentile chart live (~1s) while the run is in progress,
+    /// recomputing the layout from the terminal size on resize.
+    #[structopt(long)]
+    watch: bool,
+
+    /// Open-loop send rate in requests/sec. When set, requests are dispatched
+    /// on their precomputed schedule (t_i = t_0 + i/rate) regardless of when
+    /// earlier replies arrive, and latency is measured from the *intended*
+    /// send time. This avoids the coordinated omission of the default
+    /// closed-loop mode, where the next request only goes out after the
+    /// previous reply returns and a latency spike also pauses the sender.
+    #[structopt(long)]
+    rate: Option<f64>,
+
+    /// Emit a machine-readable report (csv or json) to stdout instead of the
+    /// TUI chart. Includes the deep-tail percentiles (p99.9, p99.99) plus
+    /// min/max/count/RTT, for diffing tail latency across builds in CI.
+    #[structopt(long)]
+    format: Option<Format>,
+
+    /// Shorthand for `--format csv`.
+    #[structopt(long, conflicts_with = "format")]
+    raw: bool,
+
+    /// Responder delay distribution. Either a weighted mix
+    /// (`5ms:65,10ms:25,100ms:3`) or a parametric form (`exp:<mean>`,
+    /// `lognormal:<mu>,<sigma>`). Defaults to the built-in mix.
+    #[structopt(long)]
+    delay_spec: Option<String>,
+}
+
+/// A fixed pool of worker threads that run boxed jobs pulled from a shared
+/// queue, so dispatching requests never spawns a thread per request.
+struct Pool {
+    tx: Option<mpsc::Sender<Box<dyn FnOnce() + Send>>>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+impl Pool {
+    fn new(size: usize) -> Self {
+        let (tx, rx) = mpsc::channel::<Box<dyn FnOnce() + Send>>();
+        let rx = Arc::new(Mutex::new(rx));
+        let workers = (0..size)
+            .map(|_| {
+                let rx = rx.clone();
+                thread::spawn(move || loop {
+                    let job = rx.lock().unwrap().recv();
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => break,
+                    }
+                })
+            })
+            .collect();
+        Pool {
+            tx: Some(tx),
+            workers,
+        }
+    }
+
+    fn execute<F: FnOnce() + Send + 'static>(&self, job: F) {
+        self.tx.as_ref().unwrap().send(Box::new(job)).unwrap();
+    }
+
+    // Close the queue and wait for all outstanding jobs to drain.
+    fn join(mut self) {
+        self.tx.take();
+        for w in self.workers {
+            let _ = w.join();
+        }
+    }
 }
 
-// Exponential delays in ms for our workers.
+// Default exponential-ish delay mix (ms, weight) for our workers.
 const DELAYS: [(u64, u64); 5] = [(5, 65), (10, 25), (15, 4), (50, 3), (100, 3)];
 
 lazy_static::lazy_static! {
     static ref HISTOGRAM: Histo = Default::default();
-    static ref DIST: WeightedAliasIndex<u64> = WeightedAliasIndex::new(DELAYS.iter().map(|item| item.1).collect()).unwrap();
+}
+
+/// A source of simulated responder delays. Keeping the sampling behind a trait
+/// lets the responders run off a configurable distribution and lets tests drive
+/// a deterministic model with a seeded RNG.
+trait LatencyModel: Send + Sync {
+    fn sample(&self, rng: &mut dyn RngCore) -> Duration;
+}
+
+/// A discrete weighted mix of fixed delays (the historical behavior).
+struct WeightedModel {
+    delays: Vec<Duration>,
+    dist: WeightedAliasIndex<u64>,
+}
+
+impl WeightedModel {
+    fn new(pairs: Vec<(Duration, u64)>) -> Result<Self, String> {
+        if pairs.is_empty() {
+            return Err("delay spec must list at least one delay".to_string());
+        }
+        let (delays, weights): (Vec<_>, Vec<_>) = pairs.into_iter().unzip();
+        let dist = WeightedAliasIndex::new(weights).map_err(|e| e.to_string())?;
+        Ok(WeightedModel { delays, dist })
+    }
+
+    // The built-in mix used when no --delay-spec is given.
+    fn default_mix() -> Self {
+        let pairs = DELAYS
+            .iter()
+            .map(|&(ms, w)| (Duration::from_millis(ms), w))
+            .collect();
+        WeightedModel::new(pairs).unwrap()
+    }
+}
+
+impl LatencyModel for WeightedModel {
+    fn sample(&self, rng: &mut dyn RngCore) -> Duration {
+        self.delays[self.dist.sample(rng)]
+    }
+}
+
+/// A parametric model drawing delays (in ms) from a continuous distribution.
+enum ParametricModel {
+    Exponential(Exp<f64>),
+    LogNormal(LogNormal<f64>),
+}
+
+impl LatencyModel for ParametricModel {
+    fn sample(&self, rng: &mut dyn RngCore) -> Duration {
+        let ms = match self {
+            ParametricModel::Exponential(d) => d.sample(rng),
+            ParametricModel::LogNormal(d) => d.sample(rng),
+        };
+        Duration::from_secs_f64(ms.max(0.0) / 1000.0)
+    }
+}
+
+// Parse a duration with a `ms`, `us`, or `s` suffix (e.g. `5ms`, `500us`, `1s`).
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (value, unit) = if let Some(v) = s.strip_suffix("ms") {
+        (v, "ms")
+    } else if let Some(v) = s.strip_suffix("us") {
+        (v, "us")
+    } else if let Some(v) = s.strip_suffix('s') {
+        (v, "s")
+    } else {
+        return Err(format!("missing time unit in `{}` (expected ms/us/s)", s));
+    };
+    let n: f64 = value
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid duration `{}`", s))?;
+    let secs = match unit {
+        "ms" => n / 1_000.0,
+        "us" => n / 1_000_000.0,
+        _ => n,
+    };
+    Ok(Duration::from_secs_f64(secs))
+}
+
+/// Parse a `--delay-spec` value into a latency model.
+///
+/// Accepts either a discrete weighted mix, `5ms:65,10ms:25,100ms:3`, or a
+/// parametric form, `exp:<mean>` or `lognormal:<mu>,<sigma>` (mu/sigma in
+/// natural-log milliseconds).
+fn parse_delay_spec(spec: &str) -> Result<Box<dyn LatencyModel>, String> {
+    let spec = spec.trim();
+    if let Some(mean) = spec.strip_prefix("exp:") {
+        let mean = parse_duration(mean)?.as_secs_f64() * 1000.0;
+        if mean <= 0.0 {
+            return Err("exponential mean must be positive".to_string());
+        }
+        let dist = Exp::new(1.0 / mean).map_err(|e| e.to_string())?;
+        return Ok(Box::new(ParametricModel::Exponential(dist)));
+    }
+    if let Some(params) = spec.strip_prefix("lognormal:") {
+        let mut it = params.split(',');
+        let mu: f64 = it
+            .next()
+            .and_then(|v| v.trim().parse().ok())
+            .ok_or("lognormal requires mu")?;
+        let sigma: f64 = it
+            .next()
+            .and_then(|v| v.trim().parse().ok())
+            .ok_or("lognormal requires sigma")?;
+        let dist = LogNormal::new(mu, sigma).map_err(|e| e.to_string())?;
+        return Ok(Box::new(ParametricModel::LogNormal(dist)));
+    }
+
+    let mut pairs = Vec::new();
+    for entry in spec.split(',') {
+        let (dur, weight) = entry
+            .split_once(':')
+            .ok_or_else(|| format!("expected `delay:weight`, got `{}`", entry))?;
+        let dur = parse_duration(dur)?;
+        let weight: u64 = weight
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid weight `{}`", weight))?;
+        pairs.push((dur, weight));
+    }
+    Ok(Box::new(WeightedModel::new(pairs)?))
+}
+
+// Number of samples recorded into the histogram.
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// Number of requests that failed and were excluded from the histogram.
+static ERRORS: AtomicUsize = AtomicUsize::new(0);
+
+/// Machine-readable output formats for scripting and CI.
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Csv,
+    Json,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            other => Err(format!("unknown format `{}` (expected csv or json)", other)),
+        }
+    }
+}
+
+// Elapsed milliseconds since `start`, discounting the network transit time so
+// we measure the service's own latency rather than the round-trip to it.
+fn elapsed_ms(start: Instant, tx_time: Duration) -> u128 {
+    let mut elapsed = start.elapsed();
+    if elapsed > tx_time {
+        elapsed -= tx_time;
+    }
+    elapsed.as_millis()
+}
+
+// Record a latency sample (in ms) into the shared histogram and bump the count.
+fn record(sample_ms: u128) {
+    HISTOGRAM.measure(sample_ms as f64);
+    COUNT.fetch_add(1, Ordering::Relaxed);
+}
+
+// The percentiles reported in machine-readable output, including the deep tail.
+const RAW_PERCENTILES: [f64; 8] = [50., 75., 90., 95., 99., 99.9, 99.99, 100.];
+
+// Emit the full percentile table plus min/max/count/RTT as CSV or JSON.
+fn emit_report(format: Format, rtt: Duration) {
+    let rtt_ms = rtt.as_secs_f64() * 1000.0;
+    let count = COUNT.load(Ordering::Relaxed);
+    let errors = ERRORS.load(Ordering::Relaxed);
+    let min = HISTOGRAM.percentile(0.0);
+    let label = |p: f64| if p >= 100.0 { "max".to_string() } else { format!("p{}", p) };
+
+    match format {
+        Format::Csv => {
+            println!("metric,value");
+            println!("rtt_ms,{}", rtt_ms);
+            println!("count,{}", count);
+            println!("errors,{}", errors);
+            println!("min,{}", min);
+            for p in &RAW_PERCENTILES {
+                println!("{},{}", label(*p), HISTOGRAM.percentile(*p));
+            }
+        }
+        Format::Json => {
+            let mut fields = vec![
+                format!("\"rtt_ms\":{}", rtt_ms),
+                format!("\"count\":{}", count),
+                format!("\"errors\":{}", errors),
+                format!("\"min\":{}", min),
+            ];
+            for p in &RAW_PERCENTILES {
+                fields.push(format!("\"{}\":{}", label(*p), HISTOGRAM.percentile(*p)));
+            }
+            println!("{{{}}}", fields.join(","));
+        }
+    }
+}
+
+// How often the --watch dashboard redraws itself.
+const DISPLAY_DELTA: Duration = Duration::from_millis(1000);
+
+// Default in-flight bound for open-loop mode when -c is 1 (its default). Kept
+// large and independent of core count so the measured tail reflects the
+// service, not the client's local parallelism; -c N with N > 1 caps it lower.
+const DEFAULT_OPEN_LOOP_WORKERS: usize = 1024;
+
+// The percentile rows shown by the bar chart, read live from the histogram.
+fn chart_data() -> Vec<(String, u64)> {
+    let mut data = vec![("  min".to_string(), HISTOGRAM.percentile(0.0) as u64)];
+    for pctl in &[50., 75., 90., 95., 99.0] {
+        data.push((format!("{:4}th", pctl), HISTOGRAM.percentile(*pctl) as u64));
+    }
+    data.push(("  max".to_string(), HISTOGRAM.percentile(100.0) as u64));
+    data
+}
+
+// Render the latency chart into `area`, reading the current percentiles out of
+// the shared histogram.
+fn draw_chart<B: Backend>(terminal: &mut Terminal<B>, area: Rect) -> io::Result<()> {
+    let owned = chart_data();
+    let data: Vec<(&str, u64)> = owned.iter().map(|(l, v)| (l.as_str(), *v)).collect();
+
+    let barchart = BarChart::default()
+        .block(Block::default().title(" Latency(ms)").borders(Borders::ALL))
+        .data(&data)
+        .max(110)
+        .bar_style(Style::default().fg(Color::Gray))
+        .bar_width(7)
+        .label_style(Style::default().add_modifier(Modifier::BOLD))
+        .value_style(Style::default().fg(Color::Black).bg(Color::Gray));
+
+    terminal.draw(|f| f.render_widget(barchart, area))?;
+    Ok(())
 }
 
 fn main() -> io::Result<()> {
     let args = Args::from_args();
 
+    // `--raw` is shorthand for `--format csv`. When a machine-readable format
+    // is requested we suppress the human banner and skip the TUI entirely.
+    let format = args.format.or(if args.raw { Some(Format::Csv) } else { None });
+    let quiet = format.is_some();
+
     // Connect to the NATS network.
     // This is like your computer connecting to WiFi or your phone connecting to the cellular network.
-    println!("Attempting to connect to NATS [{}]", &args.server);
-    let nc = nats::connect(&args.server).unwrap_or_else(|_| {
-        println!("Falling back to [demo.nats.io]");
-        nats::connect("demo.nats.io").unwrap()
+    if !quiet {
+        println!("Attempting to connect to NATS [{}]", &args.server);
+    }
+    let mut opts = if let (Some(user), Some(pass)) = (&args.user, &args.pass) {
+        nats::Options::with_user_pass(user, pass)
+    } else if let Some(token) = &args.token {
+        nats::Options::with_token(token)
+    } else if let Some(creds) = &args.creds {
+        nats::Options::with_credentials(creds)
+    } else {
+        nats::Options::new()
+    };
+    // Identify the benchmark in server monitoring (e.g. `nats-top`).
+    opts = opts.with_name("tail-latency-nats");
+    if args.tls {
+        opts = opts.tls_required(true);
+    }
+    let nc = opts.clone().connect(&args.server).unwrap_or_else(|_| {
+        if !quiet {
+            println!("Falling back to [demo.nats.io]");
+        }
+        opts.connect("demo.nats.io").unwrap()
     });
     let rtt = nc.rtt()?;
 
-    println!();
-    println!("{}:        {:?}", "RTT".bold(), rtt);
-    println!("{}: {:?}", "Responders".bold(), args.num_responders);
-    println!("{}: {:?}", "Duplicated".bold(), args.num_replicas);
-    println!("{}:   {:?}", "Requests".bold(), args.num_requests);
-    println!();
+    if !quiet {
+        println!();
+        println!("{}:        {:?}", "RTT".bold(), rtt);
+        println!("{}: {:?}", "Responders".bold(), args.num_responders);
+        println!("{}: {:?}", "Duplicated".bold(), args.num_replicas);
+        println!("{}:   {:?}", "Requests".bold(), args.num_requests);
+        println!();
+    }
+
+    // Build the responder delay model from --delay-spec, or the default mix.
+    let model: Arc<dyn LatencyModel> = match &args.delay_spec {
+        Some(spec) => parse_delay_spec(spec)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+            .into(),
+        None => Arc::new(WeightedModel::default_mix()),
+    };
 
     // Spin up our subscriptions/workers.
     // Pick an inbox in case we use something public like demo.nats.io.
@@ -71,58 +453,167 @@ fn main() -> io::Result<()> {
         let qg = format!("qg:{}", i);
         for _ in 0..args.num_responders.get() {
             // This is our service.
+            let model = model.clone();
             nc.queue_subscribe(&svc_addr, &qg)?
                 .with_handler(move |msg| {
-                    thread::sleep(Duration::from_millis(
-                        DELAYS[DIST.sample(&mut thread_rng())].0,
-                    ));
+                    thread::sleep(model.sample(&mut thread_rng()));
                     msg.respond("42")
                 });
         }
     }
 
     let tx_time: Duration = 2 * rtt;
-    let calc_elapsed = |start: Instant| {
-        let mut elapsed = start.elapsed();
-        if elapsed > tx_time {
-            elapsed -= tx_time;
-        }
-        elapsed.as_millis()
-    };
 
     // Send requests.
-    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
-    terminal.hide_cursor()?;
     let num_reqs = args.num_requests.get();
-    let sp = Spinner::new(Spinners::Dots9, format!("Sending {} requests", num_reqs));
+    let concurrency = args.concurrency.get();
+
+    // In --watch mode a background thread redraws the chart from live
+    // percentiles and recomputes the layout on SIGWINCH; otherwise we show a
+    // spinner and render once at the end.
+    let watcher = if args.watch && !quiet {
+        let done = Arc::new(AtomicBool::new(false));
+        let resized = Arc::new(AtomicBool::new(false));
+        signal_hook::flag::register(signal_hook::consts::SIGWINCH, resized.clone())?;
+        let stop = done.clone();
+        let handle = thread::spawn(move || -> io::Result<()> {
+            let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+            terminal.hide_cursor()?;
+            // Clear the banner region up front so the chart, drawn at the
+            // top-left each tick, never paints over already-printed output.
+            terminal.clear()?;
+            loop {
+                if resized.swap(false, Ordering::Relaxed) {
+                    terminal.autoresize()?;
+                    terminal.clear()?;
+                }
+                // Recompute the chart rectangle from the live terminal size so
+                // a mid-run resize never corrupts the drawing.
+                let size = terminal.size()?;
+                let area = Rect::new(0, 0, size.width.min(58), size.height.min(11));
+                draw_chart(&mut terminal, area)?;
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                thread::sleep(DISPLAY_DELTA);
+            }
+            terminal.show_cursor()?;
+            Ok(())
+        });
+        Some((done, handle))
+    } else {
+        None
+    };
+
+    let sp = if watcher.is_none() && !quiet {
+        Some(Spinner::new(
+            Spinners::Dots9,
+            format!("Sending {} requests", num_reqs),
+        ))
+    } else {
+        None
+    };
 
-    for _ in 0..num_reqs {
-        let start = Instant::now();
-        nc.request(&svc_addr, "Hello World")?;
-        HISTOGRAM.measure(calc_elapsed(start) as f64);
+    if let Some(rate) = args.rate {
+        // Open-loop: dispatch on a fixed schedule and never let a slow request
+        // hold up the next scheduled send. Dispatch is backed by a bounded
+        // worker pool, so a high -n/--rate run can't exhaust the process with
+        // one thread per request; when every worker is busy the job simply
+        // queues, and because latency is measured from the intended start that
+        // backpressure correctly inflates the tail.
+        let period = Duration::from_secs_f64(1.0 / rate);
+        let workers = if concurrency == 1 {
+            DEFAULT_OPEN_LOOP_WORKERS
+        } else {
+            concurrency
+        };
+        let pool = Pool::new(workers);
+        let t0 = Instant::now();
+        for i in 0..num_reqs {
+            // Scale by a float to avoid truncating the sample index to u32.
+            let target = t0 + period.mul_f64(i as f64);
+            let now = Instant::now();
+            if target > now {
+                thread::sleep(target - now);
+            }
+            let nc = nc.clone();
+            let svc_addr = svc_addr.clone();
+            pool.execute(move || match nc.request(&svc_addr, "Hello World") {
+                Ok(_) => record(elapsed_ms(target, tx_time)),
+                Err(_) => {
+                    ERRORS.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+        pool.join();
+    } else if concurrency == 1 {
+        // Closed-loop: one request outstanding at a time. As on the concurrent
+        // paths, a failed request is counted rather than aborting the whole run
+        // and discarding every sample collected so far.
+        for _ in 0..num_reqs {
+            let start = Instant::now();
+            match nc.request(&svc_addr, "Hello World") {
+                Ok(_) => record(elapsed_ms(start, tx_time)),
+                Err(_) => {
+                    ERRORS.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    } else {
+        // Keep `concurrency` requests in-flight via a fixed pool of workers,
+        // each measuring and recording its own reply. As on the serial path, a
+        // failed request is counted separately rather than folded into the
+        // latency histogram as a bogus ~0ms sample.
+        let pool = Pool::new(concurrency);
+        for _ in 0..num_reqs {
+            let nc = nc.clone();
+            let svc_addr = svc_addr.clone();
+            pool.execute(move || {
+                let start = Instant::now();
+                match nc.request(&svc_addr, "Hello World") {
+                    Ok(_) => record(elapsed_ms(start, tx_time)),
+                    Err(_) => {
+                        ERRORS.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+        pool.join();
     }
 
-    sp.stop();
-    terminal.show_cursor()?;
+    if let Some(sp) = sp {
+        sp.stop();
+    }
 
-    // Gather the results.
-    let mut data: Vec<(&str, u64)> = Vec::new();
-    data.push(("  min", HISTOGRAM.percentile(0.0) as u64));
-    for pctl in &[50., 75., 90., 95., 99.0] {
-        let p = HISTOGRAM.percentile(*pctl);
-        let l = format!("{:4}th", pctl);
-        data.push((Box::leak(l.into_boxed_str()), p as u64));
+    let errors = ERRORS.load(Ordering::Relaxed);
+    if errors > 0 && !quiet {
+        eprintln!("{}: {} request(s) failed and were excluded", "WARN".bold(), errors);
     }
-    data.push(("  max", HISTOGRAM.percentile(100.0) as u64));
 
-    let barchart = BarChart::default()
-        .block(Block::default().title(" Latency(ms)").borders(Borders::ALL))
-        .data(&data)
-        .max(110)
-        .bar_style(Style::default().fg(Color::Gray))
-        .bar_width(7)
-        .label_style(Style::default().add_modifier(Modifier::BOLD))
-        .value_style(Style::default().fg(Color::Black).bg(Color::Gray));
+    if let Some((done, handle)) = watcher {
+        // Signal the render loop to draw the final frame and exit.
+        done.store(true, Ordering::Relaxed);
+        let result = handle.join().unwrap();
+        if result.is_err() {
+            // The render thread bailed before restoring the cursor; do it here
+            // so an error never leaves the terminal with a hidden cursor.
+            let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+            let _ = terminal.show_cursor();
+        }
+        result?;
+        println!("\n");
+        return Ok(());
+    }
+
+    // Machine-readable output skips the TUI entirely.
+    if let Some(format) = format {
+        emit_report(format, rtt);
+        return Ok(());
+    }
+
+    // One-shot render of the final chart.
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    terminal.hide_cursor()?;
 
     const W: u16 = 58;
     const H: u16 = 11;
@@ -131,12 +622,60 @@ fn main() -> io::Result<()> {
     // I am sure there is a much better way to do this.. Not a tui expert.
     print!("{}", "\n".repeat(H.into()));
     let (_, y) = terminal.get_cursor()?;
-    let area = Rect::new(0, y - H, W, H);
-
-    terminal.draw(|f| {
-        f.render_widget(barchart, area);
-    })?;
+    draw_chart(&mut terminal, Rect::new(0, y - H, W, H))?;
 
+    terminal.show_cursor()?;
     println!("\n");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+
+    // A seeded RNG plus a fixed model makes the histogram the tool would build
+    // fully reproducible without a live NATS server or wall clock: the sampled
+    // Duration *is* the controlled time source.
+    fn histogram_from(model: &dyn LatencyModel, seed: u64, n: usize) -> Histo {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let histo: Histo = Default::default();
+        for _ in 0..n {
+            histo.measure(model.sample(&mut rng).as_millis() as f64);
+        }
+        histo
+    }
+
+    #[test]
+    fn weighted_mix_matches_expected_percentiles() {
+        let model = WeightedModel::default_mix();
+        let histo = histogram_from(&model, 0xC0FFEE, 50_000);
+        // 65% of samples are 5ms, so the median sits in the 5ms bucket.
+        let p50 = histo.percentile(50.0);
+        assert!((4.0..=8.0).contains(&p50), "p50 was {}", p50);
+        // The 3% at 100ms dominate the far tail.
+        let p99 = histo.percentile(99.0);
+        assert!((40.0..=130.0).contains(&p99), "p99 was {}", p99);
+    }
+
+    #[test]
+    fn parse_delay_spec_reads_weighted_pairs() {
+        let model = parse_delay_spec("5ms:50,10ms:50").unwrap();
+        let histo = histogram_from(model.as_ref(), 7, 20_000);
+        let p50 = histo.percentile(50.0);
+        assert!((4.0..=11.0).contains(&p50), "p50 was {}", p50);
+    }
+
+    #[test]
+    fn parse_duration_requires_a_unit() {
+        assert!(parse_duration("5").is_err());
+        assert_eq!(parse_duration("100ms").unwrap(), Duration::from_millis(100));
+        assert_eq!(parse_duration("1s").unwrap(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn parametric_specs_parse() {
+        assert!(parse_delay_spec("exp:10ms").is_ok());
+        assert!(parse_delay_spec("lognormal:1.6,0.5").is_ok());
+    }
+}